@@ -1,10 +1,10 @@
 use bytemuck::{Pod, Zeroable};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Buffer, BufferUsages, CommandEncoderDescriptor, Device, Dx12Compiler, Instance,
-    InstanceDescriptor, InstanceFlags, Queue, RenderPass, RenderPassColorAttachment,
-    RenderPassDescriptor, RenderPipeline, RequestAdapterOptions, ShaderModule, Surface,
-    SurfaceConfiguration, TextureFormat,
+    BindGroup, BindGroupLayout, Buffer, BufferUsages, CommandEncoderDescriptor, Device,
+    Dx12Compiler, Instance, InstanceDescriptor, InstanceFlags, Queue, RenderPass,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RequestAdapterOptions,
+    Sampler, ShaderModule, Surface, SurfaceConfiguration, TextureFormat, TextureView,
 };
 use winit::{
     dpi::PhysicalSize,
@@ -46,6 +46,20 @@ pub struct State {
     config: SurfaceConfiguration,
     device: Device,
     queue: Queue,
+    depth_view: TextureView,
+    camera: Camera,
+    triangle_pipeline: TrianglePipeline,
+    sprite_pipeline: SpritePipeline,
+    shape_pipeline: ShapePipeline,
+    geometry_pipeline: GeometryPipeline,
+    drawables: Vec<Drawable>,
+}
+
+/// A handle submitted to [`State`] to be drawn each frame by its owning pipeline.
+pub enum Drawable {
+    Sprite(Sprite),
+    Shape(GpuShape),
+    Geometry(GeometryBatch),
 }
 
 impl State {
@@ -101,11 +115,27 @@ impl State {
         };
         surface.configure(&device, &config);
 
+        let depth_view = create_depth_texture(&device, &config);
+
+        let camera = Camera::new(&device, config.width, config.height);
+
+        let triangle_pipeline = TrianglePipeline::new(&device, &camera.layout, config.format);
+        let sprite_pipeline = SpritePipeline::new(&device, &camera.layout, config.format);
+        let shape_pipeline = ShapePipeline::new(&device, &camera.layout, config.format);
+        let geometry_pipeline = GeometryPipeline::new(&device, &camera.layout, config.format);
+
         return Self {
             surface,
             config,
             device,
             queue,
+            depth_view,
+            camera,
+            triangle_pipeline,
+            sprite_pipeline,
+            shape_pipeline,
+            geometry_pipeline,
+            drawables: Vec::new(),
         };
     }
 
@@ -121,8 +151,6 @@ impl State {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let pipeline = TrianglePipeline::new(&self.device, output.texture.format());
-
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -134,25 +162,407 @@ impl State {
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
 
-            pipeline.draw(&mut render_pass);
+            self.draw_scene(&mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
     }
 
+    /// Queue a drawable to be rendered every frame until [`State::clear_drawables`].
+    /// Submit back-to-front (largest `z` first) so alpha blending composites correctly.
+    pub fn add_drawable(&mut self, drawable: Drawable) {
+        self.drawables.push(drawable);
+    }
+
+    /// Drop every submitted drawable.
+    pub fn clear_drawables(&mut self) {
+        self.drawables.clear();
+    }
+
+    /// Record the draw calls shared by the on-screen and offscreen render paths.
+    fn draw_scene<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.camera.bind_group, &[]);
+        self.triangle_pipeline.draw(render_pass);
+        for drawable in &self.drawables {
+            match drawable {
+                Drawable::Sprite(sprite) => self.sprite_pipeline.draw(render_pass, sprite),
+                Drawable::Shape(shape) => self.shape_pipeline.draw(render_pass, shape),
+                Drawable::Geometry(batch) => self.geometry_pipeline.draw(render_pass, batch),
+            }
+        }
+    }
+
+    /// Render a single frame into an offscreen texture of `width` x `height` instead of the
+    /// swapchain and return its pixels, row-unpadded and tightly packed.
+    ///
+    /// The offscreen texture uses the same format as the surface (`config.format`), so it
+    /// matches the pipelines built in [`State::new`]. The event-loop-free counterpart to the
+    /// windowed path, for headless rendering, thumbnails and screenshot-based tests.
+    pub fn render_offscreen(&self, width: u32, height: u32) -> Vec<u8> {
+        let format = self.config.format;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Depth Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Each row written by `copy_texture_to_buffer` must be a multiple of 256 bytes.
+        let bytes_per_pixel = format.block_copy_size(None).unwrap_or(4);
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = ((unpadded_bytes_per_row + align - 1) / align) * align;
+
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Project for the offscreen size so framing/aspect match the capture, not the window.
+        self.camera.update(&self.queue, width, height);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Offscreen Command Encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Offscreen Render Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            self.draw_scene(&mut render_pass);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &output_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // Restore the on-screen projection for the next windowed frame.
+        self.camera
+            .update(&self.queue, self.config.width, self.config.height);
+
+        let slice = output_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv().unwrap().unwrap();
+
+        let padded = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in padded.chunks_exact(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+
+        drop(padded);
+        output_buffer.unmap();
+
+        return pixels;
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         if size.width > 0 && size.height > 0 {
             self.config.width = size.width;
             self.config.height = size.height;
             self.surface.configure(&self.device, &self.config);
+            self.depth_view = create_depth_texture(&self.device, &self.config);
+            self.camera
+                .update(&self.queue, self.config.width, self.config.height);
         }
     }
+
+    pub fn load_sprite(&self, bytes: &[u8], dest: Rect, z: f32) -> Sprite {
+        return self
+            .sprite_pipeline
+            .load_sprite(&self.device, &self.queue, bytes, dest, z);
+    }
+
+    pub fn build_shape(&self, mesh: &ShapeMesh, z: f32) -> GpuShape {
+        return self.shape_pipeline.upload(&self.device, mesh, z);
+    }
+
+    pub fn create_geometry(
+        &self,
+        vertices: &[ColorVertex],
+        indices: &[u16],
+        z: f32,
+    ) -> GeometryBatch {
+        return self
+            .geometry_pipeline
+            .create_batch(&self.device, vertices, indices, z);
+    }
+
+    pub fn write_geometry(
+        &self,
+        batch: &mut GeometryBatch,
+        vertices: &[ColorVertex],
+        indices: &[u16],
+    ) {
+        batch.write(&self.device, &self.queue, vertices, indices);
+    }
+}
+
+/// Depth format used for 2D layer ordering across every pipeline.
+const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// (Re)create the surface-sized depth buffer used as the render pass' depth attachment.
+fn create_depth_texture(device: &Device, config: &SurfaceConfiguration) -> TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    return texture.create_view(&wgpu::TextureViewDescriptor::default());
+}
+
+/// Shared depth-stencil state. Depth is written and tested with `LessEqual` so a drawable's
+/// `z` layer deterministically occludes larger-`z` drawables regardless of submit order.
+/// Opaque drawables may therefore be submitted in any order; translucent ones should still
+/// be submitted back-to-front (largest `z` first) so alpha blending composites correctly.
+fn depth_stencil_state() -> wgpu::DepthStencilState {
+    return wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    };
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct LayerUniform {
+    z: f32,
+    _padding: [f32; 3],
+}
+
+/// Bind group layout for the per-draw layer (`z`) uniform read by each pipeline's vertex
+/// shader.
+fn layer_bind_group_layout(device: &Device) -> BindGroupLayout {
+    return device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Layer Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+}
+
+/// Build the layer bind group placing a drawable at depth `z` (0.0 = front, 1.0 = back).
+fn create_layer_bind_group(device: &Device, layout: &BindGroupLayout, z: f32) -> BindGroup {
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Layer Uniform"),
+        usage: BufferUsages::UNIFORM,
+        contents: bytemuck::cast_slice(&[LayerUniform {
+            z,
+            _padding: [0.0; 3],
+        }]),
+    });
+    return device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Layer Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+}
+
+/// A 2D orthographic camera letting callers draw in pixel/world units instead of raw clip
+/// space, with pan (`position`), `zoom`, and `rotation`.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera2D {
+    pub position: [f32; 2],
+    pub zoom: f32,
+    pub rotation: f32,
+}
+
+impl Camera2D {
+    /// View-projection mapping world coordinates into clip space for the given surface size.
+    pub fn view_proj(&self, width: u32, height: u32) -> [[f32; 4]; 4] {
+        let half_w = width as f32 / 2.0;
+        let half_h = height as f32 / 2.0;
+
+        let proj = cgmath::ortho(-half_w, half_w, -half_h, half_h, -1.0, 1.0);
+        let view = cgmath::Matrix4::from_scale(self.zoom)
+            * cgmath::Matrix4::from_angle_z(cgmath::Rad(-self.rotation))
+            * cgmath::Matrix4::from_translation(cgmath::vec3(
+                -self.position[0],
+                -self.position[1],
+                0.0,
+            ));
+
+        return (proj * view).into();
+    }
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        return Self {
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            rotation: 0.0,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+/// The [`Camera2D`] together with its uniform buffer and the group-0 bind group every
+/// pipeline reads its view-projection matrix from.
+pub struct Camera {
+    pub camera: Camera2D,
+    buffer: Buffer,
+    bind_group: BindGroup,
+    layout: BindGroupLayout,
+}
+
+impl Camera {
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let camera = Camera2D::default();
+
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Camera Uniform"),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&[CameraUniform {
+                view_proj: camera.view_proj(width, height),
+            }]),
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        return Self {
+            camera,
+            buffer,
+            bind_group,
+            layout,
+        };
+    }
+
+    /// Re-upload the view-projection matrix, e.g. after a resize or a pan/zoom change.
+    pub fn update(&self, queue: &Queue, width: u32, height: u32) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[CameraUniform {
+                view_proj: self.camera.view_proj(width, height),
+            }]),
+        );
+    }
 }
 
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
@@ -175,32 +585,43 @@ impl TriangleVertex {
     }
 }
 
+// Expressed in pixel/world units (see `Camera2D`) so the demo triangle stays visible at the
+// default zoom, rather than the sub-pixel speck raw clip-space coords would project to.
 const TRIANGLE_VERTICES: [TriangleVertex; 3] = [
     TriangleVertex {
-        position: [0.0, 0.5],
+        position: [0.0, 200.0],
     },
     TriangleVertex {
-        position: [-0.5, -0.5],
+        position: [-200.0, -200.0],
     },
     TriangleVertex {
-        position: [0.5, -0.5],
+        position: [200.0, -200.0],
     },
 ];
 
 pub struct TrianglePipeline {
     pipeline: RenderPipeline,
     vertex_buffer: Buffer,
+    layer_bind_group: BindGroup,
 }
 
 impl TrianglePipeline {
-    pub fn new(device: &Device, format: TextureFormat) -> Self {
+    pub fn new(device: &Device, camera_layout: &BindGroupLayout, format: TextureFormat) -> Self {
         let vertex_buffer = Self::create_vertex_buffer(device);
 
         let shader = Self::create_shader_module(device);
 
+        let layer_layout = layer_bind_group_layout(device);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &layer_layout],
+            push_constant_ranges: &[],
+        });
+
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Pipeline"),
-            layout: None,
+            layout: Some(&layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
@@ -224,7 +645,7 @@ impl TrianglePipeline {
                 polygon_mode: wgpu::PolygonMode::Fill,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: Some(depth_stencil_state()),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -232,14 +653,21 @@ impl TrianglePipeline {
             },
             multiview: None,
         });
+
+        // The built-in triangle sits at the front (z = 0.0); it carries the same layer
+        // uniform as the other pipelines purely to satisfy the shared bind-group layout.
+        let layer_bind_group = create_layer_bind_group(device, &layer_layout, 0.0);
+
         return Self {
             pipeline,
             vertex_buffer,
+            layer_bind_group,
         };
     }
 
     pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
         render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.layer_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.draw(0..TRIANGLE_VERTICES.len() as _, 0..1);
     }
@@ -259,3 +687,733 @@ impl TrianglePipeline {
         });
     }
 }
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct SpriteVertex {
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+}
+
+impl SpriteVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        return wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        };
+    }
+}
+
+/// Axis-aligned destination rectangle in pixel/world units (see [`Camera2D`]), used to place
+/// a sprite.
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+const SPRITE_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+/// A decoded image uploaded to the GPU together with the quad it is blitted onto.
+pub struct Sprite {
+    bind_group: BindGroup,
+    layer_bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+}
+
+impl Sprite {
+    fn quad_vertices(dest: Rect) -> [SpriteVertex; 4] {
+        return [
+            SpriteVertex {
+                position: [dest.x, dest.y + dest.h],
+                tex_coords: [0.0, 0.0],
+            },
+            SpriteVertex {
+                position: [dest.x, dest.y],
+                tex_coords: [0.0, 1.0],
+            },
+            SpriteVertex {
+                position: [dest.x + dest.w, dest.y],
+                tex_coords: [1.0, 1.0],
+            },
+            SpriteVertex {
+                position: [dest.x + dest.w, dest.y + dest.h],
+                tex_coords: [1.0, 0.0],
+            },
+        ];
+    }
+
+    /// Move the sprite's quad to a new destination rect without re-uploading the texture.
+    pub fn set_dest(&self, queue: &Queue, dest: Rect) {
+        queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&Self::quad_vertices(dest)),
+        );
+    }
+}
+
+pub struct SpritePipeline {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    layer_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl SpritePipeline {
+    pub fn new(device: &Device, camera_layout: &BindGroupLayout, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sprite Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("sprite.wgsl").into()),
+        });
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sprite Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let layer_layout = layer_bind_group_layout(device);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &bind_group_layout, &layer_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sprite Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SpriteVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(depth_stencil_state()),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sprite Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        return Self {
+            pipeline,
+            bind_group_layout,
+            layer_layout,
+            sampler,
+        };
+    }
+
+    /// Decode an RGBA image with the `image` crate, upload it into a texture and build a
+    /// ready-to-draw [`Sprite`] covering `dest` at depth `z`.
+    pub fn load_sprite(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        bytes: &[u8],
+        dest: Rect,
+        z: f32,
+    ) -> Sprite {
+        let image = image::load_from_memory(bytes).unwrap().to_rgba8();
+        let (width, height) = image.dimensions();
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Sprite Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sprite Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Sprite Vertex"),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&Sprite::quad_vertices(dest)),
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Sprite Index"),
+            usage: BufferUsages::INDEX,
+            contents: bytemuck::cast_slice(&SPRITE_INDICES),
+        });
+
+        let layer_bind_group = create_layer_bind_group(device, &self.layer_layout, z);
+
+        return Sprite {
+            bind_group,
+            layer_bind_group,
+            vertex_buffer,
+            index_buffer,
+        };
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, sprite: &'a Sprite) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &sprite.bind_group, &[]);
+        render_pass.set_bind_group(2, &sprite.layer_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, sprite.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(sprite.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..SPRITE_INDICES.len() as _, 0, 0..1);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct ShapeVertex {
+    pub position: [f32; 2],
+}
+
+impl ShapeVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![
+        0 => Float32x2
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        return wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct ShapeUniform {
+    color: [f32; 4],
+}
+
+/// Maps every point produced by lyon's tessellators onto a [`ShapeVertex`].
+struct ShapeVertexCtor;
+
+impl lyon_tessellation::FillVertexConstructor<ShapeVertex> for ShapeVertexCtor {
+    fn new_vertex(&mut self, vertex: lyon_tessellation::FillVertex) -> ShapeVertex {
+        return ShapeVertex {
+            position: vertex.position().to_array(),
+        };
+    }
+}
+
+impl lyon_tessellation::StrokeVertexConstructor<ShapeVertex> for ShapeVertexCtor {
+    fn new_vertex(&mut self, vertex: lyon_tessellation::StrokeVertex) -> ShapeVertex {
+        return ShapeVertex {
+            position: vertex.position().to_array(),
+        };
+    }
+}
+
+/// An indexed mesh tessellated from a vector [`Shape`], ready to be uploaded to the GPU.
+pub struct ShapeMesh {
+    pub vertices: Vec<ShapeVertex>,
+    pub indices: Vec<u16>,
+    pub color: [f32; 4],
+}
+
+/// A distilled 2D vector shape built from a `lyon` path, turned into a [`ShapeMesh`] by
+/// filling or stroking it.
+pub struct Shape {
+    path: lyon_path::Path,
+}
+
+impl Shape {
+    pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Self {
+        let mut builder = lyon_path::Path::builder();
+        builder.add_rectangle(
+            &lyon_path::geom::Box2D::new(
+                lyon_path::math::point(x, y),
+                lyon_path::math::point(x + w, y + h),
+            ),
+            lyon_path::Winding::Positive,
+        );
+        return Self {
+            path: builder.build(),
+        };
+    }
+
+    pub fn rounded_rect(x: f32, y: f32, w: f32, h: f32, radius: f32) -> Self {
+        let mut builder = lyon_path::Path::builder();
+        builder.add_rounded_rectangle(
+            &lyon_path::geom::Box2D::new(
+                lyon_path::math::point(x, y),
+                lyon_path::math::point(x + w, y + h),
+            ),
+            &lyon_path::builder::BorderRadii::new(radius),
+            lyon_path::Winding::Positive,
+        );
+        return Self {
+            path: builder.build(),
+        };
+    }
+
+    pub fn circle(x: f32, y: f32, radius: f32) -> Self {
+        let mut builder = lyon_path::Path::builder();
+        builder.add_circle(
+            lyon_path::math::point(x, y),
+            radius,
+            lyon_path::Winding::Positive,
+        );
+        return Self {
+            path: builder.build(),
+        };
+    }
+
+    pub fn polyline(points: &[[f32; 2]], closed: bool) -> Self {
+        let mut builder = lyon_path::Path::builder();
+        if let Some((first, rest)) = points.split_first() {
+            builder.begin(lyon_path::math::point(first[0], first[1]));
+            for point in rest {
+                builder.line_to(lyon_path::math::point(point[0], point[1]));
+            }
+            builder.end(closed);
+        }
+        return Self {
+            path: builder.build(),
+        };
+    }
+
+    pub fn fill(&self, color: [f32; 4]) -> ShapeMesh {
+        let mut geometry: lyon_tessellation::VertexBuffers<ShapeVertex, u16> =
+            lyon_tessellation::VertexBuffers::new();
+        let mut tessellator = lyon_tessellation::FillTessellator::new();
+        tessellator
+            .tessellate_path(
+                &self.path,
+                &lyon_tessellation::FillOptions::default(),
+                &mut lyon_tessellation::BuffersBuilder::new(&mut geometry, ShapeVertexCtor),
+            )
+            .unwrap();
+        return ShapeMesh {
+            vertices: geometry.vertices,
+            indices: geometry.indices,
+            color,
+        };
+    }
+
+    pub fn stroke(&self, width: f32, color: [f32; 4]) -> ShapeMesh {
+        let mut geometry: lyon_tessellation::VertexBuffers<ShapeVertex, u16> =
+            lyon_tessellation::VertexBuffers::new();
+        let mut tessellator = lyon_tessellation::StrokeTessellator::new();
+        tessellator
+            .tessellate_path(
+                &self.path,
+                &lyon_tessellation::StrokeOptions::default().with_line_width(width),
+                &mut lyon_tessellation::BuffersBuilder::new(&mut geometry, ShapeVertexCtor),
+            )
+            .unwrap();
+        return ShapeMesh {
+            vertices: geometry.vertices,
+            indices: geometry.indices,
+            color,
+        };
+    }
+}
+
+/// A [`ShapeMesh`] uploaded into GPU buffers together with its color uniform.
+pub struct GpuShape {
+    bind_group: BindGroup,
+    layer_bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+}
+
+pub struct ShapePipeline {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    layer_layout: BindGroupLayout,
+}
+
+impl ShapePipeline {
+    pub fn new(device: &Device, camera_layout: &BindGroupLayout, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shape Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shape.wgsl").into()),
+        });
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shape Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let layer_layout = layer_bind_group_layout(device);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shape Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &bind_group_layout, &layer_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shape Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ShapeVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(depth_stencil_state()),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        return Self {
+            pipeline,
+            bind_group_layout,
+            layer_layout,
+        };
+    }
+
+    pub fn upload(&self, device: &Device, mesh: &ShapeMesh, z: f32) -> GpuShape {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Shape Vertex"),
+            usage: BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(&mesh.vertices),
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Shape Index"),
+            usage: BufferUsages::INDEX,
+            contents: bytemuck::cast_slice(&mesh.indices),
+        });
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Shape Uniform"),
+            usage: BufferUsages::UNIFORM,
+            contents: bytemuck::cast_slice(&[ShapeUniform { color: mesh.color }]),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shape Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layer_bind_group = create_layer_bind_group(device, &self.layer_layout, z);
+
+        return GpuShape {
+            bind_group,
+            layer_bind_group,
+            vertex_buffer,
+            index_buffer,
+            index_count: mesh.indices.len() as u32,
+        };
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, shape: &'a GpuShape) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &shape.bind_group, &[]);
+        render_pass.set_bind_group(2, &shape.layer_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, shape.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(shape.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..shape.index_count, 0, 0..1);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct ColorVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl ColorVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x4
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        return wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        };
+    }
+}
+
+/// A reusable vertex/index buffer pair for user-supplied indexed geometry. The buffers are
+/// grown to the largest batch seen so far and otherwise re-used across frames.
+pub struct GeometryBatch {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    layer_bind_group: BindGroup,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    index_count: u32,
+}
+
+impl GeometryBatch {
+    pub fn new(
+        device: &Device,
+        layer_layout: &BindGroupLayout,
+        vertices: &[ColorVertex],
+        indices: &[u16],
+        z: f32,
+    ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Geometry Vertex"),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(vertices),
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Geometry Index"),
+            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(indices),
+        });
+
+        let layer_bind_group = create_layer_bind_group(device, layer_layout, z);
+
+        return Self {
+            vertex_buffer,
+            index_buffer,
+            layer_bind_group,
+            vertex_capacity: vertices.len(),
+            index_capacity: indices.len(),
+            index_count: indices.len() as u32,
+        };
+    }
+
+    /// Upload a new mesh, reallocating only when it is larger than any batch seen so far.
+    pub fn write(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        vertices: &[ColorVertex],
+        indices: &[u16],
+    ) {
+        if vertices.len() > self.vertex_capacity {
+            self.vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Geometry Vertex"),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(vertices),
+            });
+            self.vertex_capacity = vertices.len();
+        } else {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        }
+
+        if indices.len() > self.index_capacity {
+            self.index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Geometry Index"),
+                usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                contents: bytemuck::cast_slice(indices),
+            });
+            self.index_capacity = indices.len();
+        } else {
+            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(indices));
+        }
+
+        self.index_count = indices.len() as u32;
+    }
+}
+
+pub struct GeometryPipeline {
+    pipeline: RenderPipeline,
+    layer_layout: BindGroupLayout,
+}
+
+impl GeometryPipeline {
+    pub fn new(device: &Device, camera_layout: &BindGroupLayout, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Geometry Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("geometry.wgsl").into()),
+        });
+
+        let layer_layout = layer_bind_group_layout(device);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Geometry Pipeline Layout"),
+            bind_group_layouts: &[camera_layout, &layer_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Geometry Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ColorVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(depth_stencil_state()),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        return Self {
+            pipeline,
+            layer_layout,
+        };
+    }
+
+    /// Build a reusable [`GeometryBatch`] for this pipeline at depth `z`.
+    pub fn create_batch(
+        &self,
+        device: &Device,
+        vertices: &[ColorVertex],
+        indices: &[u16],
+        z: f32,
+    ) -> GeometryBatch {
+        return GeometryBatch::new(device, &self.layer_layout, vertices, indices, z);
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, batch: &'a GeometryBatch) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &batch.layer_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(batch.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..batch.index_count, 0, 0..1);
+    }
+}